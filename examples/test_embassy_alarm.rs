@@ -0,0 +1,66 @@
+//! Checks that the embassy driver's alarm path keeps `now()` monotonic across a SysTick rollover
+//! boundary, including the reload-shortening `set_alarm` does to fire close to the deadline.
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use cortex_m::Peripherals as CorePeripherals;
+use cortex_m_rt::entry;
+use cortex_m_semihosting::{debug, hprintln};
+use panic_halt as _;
+use systick_timebase::{SysTickTimebase, SystClkSource};
+
+const FREQ: u32 = 12_000_000;
+
+static FIRED: AtomicBool = AtomicBool::new(false);
+
+fn callback(_ctx: *mut ()) {
+    FIRED.store(true, Ordering::Release);
+}
+
+#[entry]
+fn main() -> ! {
+    let core = CorePeripherals::take().unwrap();
+    let timebase = SysTickTimebase::<FREQ>::new(core.SYST, SystClkSource::Core, FREQ);
+    systick_timebase::embassy::init::<FREQ>();
+
+    // Burn most of a 2**24-tick period first so the alarm we arm next straddles the rollover.
+    while timebase.read().ticks() < 16_700_000 {}
+
+    let alarm = unsafe { embassy_time_driver::allocate_alarm() }.expect("no alarms free");
+    embassy_time_driver::set_alarm_callback(alarm, callback, core::ptr::null_mut());
+
+    let now = embassy_time_driver::now();
+    let armed = embassy_time_driver::set_alarm(alarm, now + 200_000);
+    if !armed {
+        hprintln!("set_alarm reported an already-past deadline unexpectedly").ok();
+        debug::exit(debug::EXIT_FAILURE);
+        loop {}
+    }
+
+    let mut previous = now;
+    loop {
+        let current = embassy_time_driver::now();
+        if current < previous {
+            hprintln!(
+                "now() went backwards across the rollover: {} -> {}",
+                previous,
+                current
+            )
+            .ok();
+            debug::exit(debug::EXIT_FAILURE);
+            break;
+        }
+        previous = current;
+
+        if FIRED.load(Ordering::Acquire) {
+            hprintln!("Alarm fired across a rollover without now() going backwards").ok();
+            debug::exit(debug::EXIT_SUCCESS);
+            break;
+        }
+    }
+
+    loop {}
+}
@@ -0,0 +1,61 @@
+//! Checks that `read_cs()` agrees with the lock-free `read()` across a SysTick rollover boundary.
+
+#![no_main]
+#![no_std]
+
+use cortex_m::Peripherals as CorePeripherals;
+use cortex_m_rt::entry;
+use cortex_m_semihosting::{debug, hprintln};
+use panic_halt as _;
+
+const FREQ: u32 = 12_000_000;
+
+#[entry]
+fn main() -> ! {
+    let core = CorePeripherals::take().unwrap();
+    let timebase = systick_timebase::SysTickTimebase::<FREQ>::new(
+        core.SYST,
+        systick_timebase::SystClkSource::Core,
+        FREQ,
+    );
+
+    let mut previous = timebase.read_cs();
+    loop {
+        let lockfree = timebase.read();
+        let cs = timebase.read_cs();
+
+        if cs < previous {
+            hprintln!(
+                "read_cs() went backwards: {:08X} -> {:08X}",
+                previous.ticks(),
+                cs.ticks()
+            )
+            .ok();
+            debug::exit(debug::EXIT_FAILURE);
+            break;
+        }
+
+        // The two reads aren't atomic with each other, so they may disagree by a handful of
+        // ticks, but shouldn't diverge wildly.
+        let drift = if lockfree.ticks() > cs.ticks() {
+            lockfree.ticks() - cs.ticks()
+        } else {
+            cs.ticks() - lockfree.ticks()
+        };
+        if drift > 1_000 {
+            hprintln!("read() and read_cs() diverged by {} ticks", drift).ok();
+            debug::exit(debug::EXIT_FAILURE);
+            break;
+        }
+
+        previous = cs;
+
+        if cs.ticks() > (2 << 24) {
+            hprintln!("read_cs() tracked read() correctly across a rollover").ok();
+            debug::exit(debug::EXIT_SUCCESS);
+            break;
+        }
+    }
+
+    loop {}
+}
@@ -0,0 +1,61 @@
+//! Checks that `SysTickMonotonic::set_compare`/`on_interrupt` keep `now()` monotonic across a
+//! SysTick rollover boundary, including compares that shorten the live reload.
+
+#![no_main]
+#![no_std]
+
+use cortex_m::Peripherals as CorePeripherals;
+use cortex_m_rt::entry;
+use cortex_m_semihosting::{debug, hprintln};
+use panic_halt as _;
+use rtic_monotonic::Monotonic;
+use systick_timebase::rtic::SysTickMonotonic;
+use systick_timebase::{SysTickTimebase, TBContainer, TBDuration};
+
+const FREQ: u32 = 12_000_000;
+
+#[entry]
+fn main() -> ! {
+    let core = CorePeripherals::take().unwrap();
+    let timebase =
+        SysTickTimebase::<FREQ>::new(core.SYST, systick_timebase::SystClkSource::Core, FREQ);
+    let mut mono = SysTickMonotonic::new(timebase);
+    unsafe {
+        mono.reset();
+    }
+
+    // Arm a handful of compares that straddle the 2**24 rollover boundary, and make sure `now()`
+    // never goes backwards across any of them.
+    let mut previous = mono.now();
+    let offsets: [TBContainer; 5] = [100, 1_000, 16_000_000, 16_800_000, 17_000_000];
+    for &offset in &offsets {
+        let target = previous + TBDuration::<FREQ>::from_ticks(offset);
+        mono.set_compare(target);
+
+        loop {
+            let now = mono.now();
+            if now < previous {
+                hprintln!(
+                    "Monotonicity violated: {:08X} -> {:08X}",
+                    previous.ticks(),
+                    now.ticks()
+                )
+                .ok();
+                debug::exit(debug::EXIT_FAILURE);
+                loop {}
+            }
+            previous = now;
+            if now >= target {
+                break;
+            }
+        }
+
+        mono.on_interrupt();
+        previous = mono.now();
+    }
+
+    hprintln!("All compares fired without breaking monotonicity").ok();
+    debug::exit(debug::EXIT_SUCCESS);
+
+    loop {}
+}
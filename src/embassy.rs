@@ -0,0 +1,223 @@
+//! [`embassy_time_driver::Driver`] implementation backed by [`systick`](cortex_m::peripheral::SYST).
+//!
+//! [`now()`](Driver::now) reuses the same extended tick count produced by
+//! [`SysTickTimebase::read`](crate::SysTickTimebase::read), scaled from the `FREQ` passed to
+//! [`init`] down to the compile-time `TICK_HZ` expected by `embassy-time`. Because `SysTick` has
+//! no compare register, [`set_alarm`](Driver::set_alarm) works the same way
+//! [`rtic::SysTickMonotonic::set_compare`](crate::rtic::SysTickMonotonic) does: it temporarily
+//! lowers the reload value so the exception fires close to the soonest pending alarm's deadline,
+//! instead of waiting for the next ordinary 2**24-cycle rollover.
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::{Peripherals, SYST};
+use cortex_m_rt::exception;
+use critical_section::Mutex;
+use embassy_time_driver::{AlarmHandle, Driver};
+
+use crate::{note_elapsed_period, read_ticks, ARMED_RELOAD, SYSTICK_RELOAD};
+
+/// Number of alarms we can track concurrently. `embassy-time` typically only allocates one per
+/// executor, but a handful of slots keeps us robust to multiple callers.
+const ALARM_COUNT: usize = 4;
+
+#[derive(Clone, Copy)]
+struct AlarmState {
+    /// Deadline, in native SysTick ticks. `None` means the slot is allocated but has no alarm
+    /// armed yet.
+    deadline: Option<u64>,
+    callback: fn(*mut ()),
+    ctx: *mut (),
+}
+
+impl AlarmState {
+    const fn unarmed() -> Self {
+        Self {
+            deadline: None,
+            callback: |_| {},
+            ctx: core::ptr::null_mut(),
+        }
+    }
+}
+
+struct State {
+    alarms: [Option<AlarmState>; ALARM_COUNT],
+}
+
+struct SysTickDriver {
+    state: Mutex<RefCell<State>>,
+    /// The `FREQ` the owning [`SysTickTimebase`](crate::SysTickTimebase) was constructed with, set
+    /// once via [`init`]. Used to scale native ticks into embassy's `TICK_HZ`.
+    freq_hz: AtomicU32,
+}
+
+impl SysTickDriver {
+    const fn new() -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(State {
+                alarms: [None; ALARM_COUNT],
+            })),
+            freq_hz: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the `FREQ` passed to [`init`].
+    ///
+    /// # Panics
+    /// asserts that [`init`] has been called, the same way
+    /// [`SysTickTimebase::new`](crate::SysTickTimebase::new) checks its `sysclk` parameter.
+    fn freq_hz(&self) -> u64 {
+        let freq = self.freq_hz.load(Ordering::Acquire);
+        assert!(
+            freq != 0,
+            "embassy::init must be called before the driver is used"
+        );
+        u64::from(freq)
+    }
+
+    /// Converts a native SysTick tick count into embassy's `TICK_HZ`.
+    fn to_embassy_ticks(&self, native_ticks: u64) -> u64 {
+        native_ticks * embassy_time_driver::TICK_HZ / self.freq_hz()
+    }
+
+    /// Converts an embassy `TICK_HZ` timestamp into a native SysTick tick count.
+    fn to_native_ticks(&self, embassy_ticks: u64) -> u64 {
+        embassy_ticks * self.freq_hz() / embassy_time_driver::TICK_HZ
+    }
+
+    /// Services the periodic `SysTick` exception: folds the ticks covered by the period that just
+    /// finished into the crate's shared tick accounting, fires any alarm whose deadline has
+    /// passed, then reprograms the reload toward the soonest still-pending deadline (or back to
+    /// free-running).
+    fn on_tick(&self) {
+        critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+
+            note_elapsed_period(ARMED_RELOAD.load(Ordering::Acquire));
+
+            let now = u64::from(read_ticks());
+            for slot in state.alarms.iter_mut().flatten() {
+                if slot.deadline.is_some_and(|deadline| now >= deadline) {
+                    let callback = slot.callback;
+                    let ctx = slot.ctx;
+                    slot.deadline = None;
+                    callback(ctx);
+                }
+            }
+
+            self.rearm(&mut state);
+        });
+    }
+
+    /// Reprograms the reload toward the soonest pending alarm deadline if there is one sooner
+    /// than a full rollover period away, otherwise restores the normal free-running reload if it
+    /// had previously been shortened. Leaves the hardware untouched when it's already in the
+    /// right state, so we don't disturb `SysTick`'s automatic reload on an ordinary rollover.
+    fn rearm(&self, state: &mut State) {
+        let now = u64::from(read_ticks());
+        let soonest = state
+            .alarms
+            .iter()
+            .flatten()
+            .filter_map(|alarm| alarm.deadline)
+            .filter(|&deadline| deadline > now)
+            .min();
+
+        let target_reload = match soonest {
+            Some(deadline) if deadline - now < u64::from(SYSTICK_RELOAD) => {
+                (deadline - now).max(1) as u32
+            }
+            _ => SYSTICK_RELOAD,
+        };
+
+        let armed_reload = ARMED_RELOAD.load(Ordering::Acquire);
+        if target_reload == armed_reload {
+            // Already in the right state: don't disturb `SysTick`'s live countdown.
+            return;
+        }
+
+        // SAFETY: SysTick is only ever reprogrammed from within a `critical_section`, and every
+        // access here is a self-contained disable/set/clear/enable sequence.
+        let mut systick = unsafe { Peripherals::steal() }.SYST;
+
+        // `clear_current()` below discards whatever the in-flight period had already counted, so
+        // fold that partial progress into the shared tick accounting before we throw it away.
+        let consumed = u64::from(armed_reload) - u64::from(SYST::get_current());
+        note_elapsed_period(consumed as u32);
+
+        systick.disable_counter();
+        systick.set_reload(target_reload);
+        systick.clear_current();
+        systick.enable_counter();
+        ARMED_RELOAD.store(target_reload, Ordering::Release);
+    }
+}
+
+// SAFETY: all mutable state lives behind `critical_section::Mutex`, so it's sound to share
+// `SysTickDriver` across execution contexts.
+unsafe impl Send for SysTickDriver {}
+unsafe impl Sync for SysTickDriver {}
+
+impl Driver for SysTickDriver {
+    fn now(&self) -> u64 {
+        self.to_embassy_ticks(u64::from(read_ticks()))
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+            for (i, slot) in state.alarms.iter_mut().enumerate() {
+                if slot.is_none() {
+                    *slot = Some(AlarmState::unarmed());
+                    return Some(AlarmHandle::new(i as u8));
+                }
+            }
+            None
+        })
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+            if let Some(slot) = state.alarms[alarm.id() as usize].as_mut() {
+                slot.callback = callback;
+                slot.ctx = ctx;
+            }
+        });
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        let native_deadline = self.to_native_ticks(timestamp);
+
+        if native_deadline <= u64::from(read_ticks()) {
+            // already in the past: tell embassy to fire it immediately instead of arming us.
+            return false;
+        }
+
+        critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+            if let Some(slot) = state.alarms[alarm.id() as usize].as_mut() {
+                slot.deadline = Some(native_deadline);
+            }
+            self.rearm(&mut state);
+        });
+
+        true
+    }
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: SysTickDriver = SysTickDriver::new());
+
+/// Records the tick frequency so [`Driver::now`] and [`Driver::set_alarm`] can convert between
+/// native SysTick ticks and embassy's `TICK_HZ`. Call this once, with the same `FREQ` the
+/// [`SysTickTimebase`](crate::SysTickTimebase) registered with embassy was constructed with,
+/// before the embassy executor starts running.
+pub fn init<const FREQ: u32>() {
+    DRIVER.freq_hz.store(FREQ, Ordering::Release);
+}
+
+#[exception]
+#[allow(non_snake_case)]
+fn SysTick() {
+    DRIVER.on_tick();
+}
@@ -0,0 +1,62 @@
+//! Free-function coarse clock API, for callers that don't want to thread a `&`[`SysTickTimebase`]
+//! reference through every call (interrupt handlers, leaf drivers, etc).
+//!
+//! Call [`init`] once after constructing the crate's [`SysTickTimebase`], with the same `FREQ` it
+//! was created with. [`millis`], [`micros`], and [`now`] then read the same extended tick state
+//! [`SysTickTimebase::read`] does, but return `None` instead of a garbage reading if called before
+//! [`init`].
+//!
+//! [`SysTickTimebase`]: crate::SysTickTimebase
+//! [`SysTickTimebase::read`]: crate::SysTickTimebase::read
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::{read_ticks, TBInstant};
+
+/// Whether [`init`] has been called yet.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+/// The `FREQ` passed to [`init`], used to scale ticks into milliseconds/microseconds.
+static FREQ_HZ: AtomicU32 = AtomicU32::new(0);
+
+/// Records the tick frequency so [`millis`], [`micros`], and [`now`] can be called without access
+/// to the [`SysTickTimebase`](crate::SysTickTimebase) itself.
+///
+/// `FREQ` should match the one the timebase was constructed with.
+pub fn init<const FREQ: u32>() {
+    FREQ_HZ.store(FREQ, Ordering::Release);
+    INITIALIZED.store(true, Ordering::Release);
+}
+
+/// Returns the current extended tick count as a [`TBInstant`], or `None` if [`init`] hasn't been
+/// called yet.
+///
+/// # Panics
+/// asserts that the compile time constant `FREQ` matches the one passed to [`init`], the same way
+/// [`SysTickTimebase::new`](crate::SysTickTimebase::new) checks its `sysclk` parameter.
+#[must_use]
+pub fn now<const FREQ: u32>() -> Option<TBInstant<FREQ>> {
+    INITIALIZED.load(Ordering::Acquire).then(|| {
+        assert!(FREQ == FREQ_HZ.load(Ordering::Acquire));
+        TBInstant::<FREQ>::from_ticks(read_ticks())
+    })
+}
+
+/// Returns the milliseconds elapsed since the timebase was started, or `None` if [`init`] hasn't
+/// been called yet.
+#[must_use]
+pub fn millis() -> Option<u64> {
+    elapsed_ticks().map(|(ticks, freq)| ticks * 1_000 / u64::from(freq))
+}
+
+/// Returns the microseconds elapsed since the timebase was started, or `None` if [`init`] hasn't
+/// been called yet.
+#[must_use]
+pub fn micros() -> Option<u64> {
+    elapsed_ticks().map(|(ticks, freq)| ticks * 1_000_000 / u64::from(freq))
+}
+
+/// Returns `(ticks, freq_hz)` if [`init`] has been called, otherwise `None`.
+fn elapsed_ticks() -> Option<(u64, u32)> {
+    INITIALIZED
+        .load(Ordering::Acquire)
+        .then(|| (u64::from(read_ticks()), FREQ_HZ.load(Ordering::Acquire)))
+}
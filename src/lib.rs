@@ -40,17 +40,69 @@
 //!
 //! enables the return type to be `u64` instead of `u32`.
 //!
+//! ### `embedded-hal-1`
+//!
+//! implements embedded-hal 1.0's [`DelayNs`](embedded_hal_1::delay::DelayNs) trait, which
+//! collapses the `embedded-hal` 0.2 `DelayMs`/`DelayUs` traits above into a single `delay_ns`.
+//!
+//! ### `embassy`
+//!
+//! registers this crate as the global [`embassy-time`](embassy_time_driver) driver via
+//! [`embassy_time_driver::time_driver_impl!`], so [`embassy_time::Timer`] and friends are backed by
+//! [`SysTick`]. Implies the same extended tick tracking as `extended` (the two may be used
+//! together or `embassy` may be used on its own). Call [`embassy::init`] once, with the same
+//! `FREQ` the [`SysTickTimebase`] was constructed with, before the embassy executor starts.
+//!
+//! ### `rtic`
+//!
+//! adds [`rtic::SysTickMonotonic`], a wrapper over [`SysTickTimebase`] implementing
+//! [`rtic_monotonic::Monotonic`] so it can be `#[monotonic]`-scheduled against in an RTIC 1
+//! application. Also implies the same extended tick tracking as `extended`.
+//!
+//! ### `global`
+//!
+//! adds [`global`], a free-function `millis()`/`micros()`/`now()` API for callers that don't have
+//! a `&SysTickTimebase` handy, after a one-time [`global::init`].
+//!
+//! ### `critical-section`
+//!
+//! adds [`SysTickTimebase::read_cs`], an alternative to [`SysTickTimebase::read`] that reads
+//! [`SYST`] once inside a [`critical_section::with`] guard instead of relying on a lock-free
+//! double read. Only available together with one of `extended`, `embassy`, `rtic` or `global`.
+//!
 //! [`SYST`]: cortex_m::peripheral::SYST
 //! [`SysTick`]: `cortex_m::peripheral::scb::Exception::SysTick`
 #![cfg_attr(not(test), no_std)]
 
-#[cfg(feature = "extended")]
+#[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
 use atomic_polyfill::{AtomicU32, Ordering};
+#[cfg(all(
+    feature = "container-u64",
+    any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global")
+))]
+use atomic_polyfill::AtomicU64;
 pub use cortex_m::peripheral::syst::SystClkSource;
+#[cfg(all(
+    feature = "critical-section",
+    any(
+        feature = "extended",
+        feature = "embassy",
+        feature = "rtic",
+        feature = "global"
+    )
+))]
+use cortex_m::peripheral::SCB;
 use cortex_m::peripheral::SYST;
 #[cfg(feature = "embedded-hal")]
 use embedded_hal::blocking::delay::{DelayMs, DelayUs};
 
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(feature = "global")]
+pub mod global;
+#[cfg(feature = "rtic")]
+pub mod rtic;
+
 /// The container we return when reading out the timebase.
 #[cfg(feature = "container-u64")]
 pub type TBContainer = u64;
@@ -60,15 +112,55 @@ pub type TBContainer = u32;
 /// Our instant type
 pub type TBInstant<const FREQ: u32> = fugit::Instant<TBContainer, 1, FREQ>;
 
-#[cfg(feature = "extended")]
-/// Tracker of `systick` cycle count overflows to extend systick's 24 bit timer
-static ROLLOVER_COUNT: AtomicU32 = AtomicU32::new(0);
+/// Our duration type
+pub type TBDuration<const FREQ: u32> = fugit::Duration<TBContainer, 1, FREQ>;
+
+/// The atomic width matching [`TBContainer`], used by [`ELAPSED_TICKS_BASE`] so it can hold a raw
+/// tick count rather than just a rollover count.
+#[cfg(all(
+    feature = "container-u64",
+    any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global")
+))]
+pub(crate) type TickAtomic = AtomicU64;
+#[cfg(all(
+    not(feature = "container-u64"),
+    any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global")
+))]
+pub(crate) type TickAtomic = AtomicU32;
+
+#[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
+/// Ticks accumulated from every completed reload period that's already been accounted for, i.e.
+/// everything before the period [`ARMED_RELOAD`] currently covers.
+///
+/// This used to be a count of full [`SYSTICK_RESOLUTION`] rollovers, but `embassy` and `rtic` can
+/// temporarily shorten the live reload to fire close to an alarm/compare deadline, and a shortened
+/// period is worth fewer ticks than a full rollover. So instead this tracks raw ticks directly:
+/// whatever reload was actually armed (full or shortened) gets folded in here as soon as it
+/// finishes, via [`note_elapsed_period`].
+pub(crate) static ELAPSED_TICKS_BASE: TickAtomic = TickAtomic::new(0);
+
+#[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
+/// The reload value currently programmed into [`systick`](cortex_m::peripheral::SYST): either the
+/// full [`SYSTICK_RELOAD`], or a value `embassy`/`rtic` shortened to fire closer to a pending
+/// alarm/compare deadline.
+pub(crate) static ARMED_RELOAD: AtomicU32 = AtomicU32::new(SYSTICK_RELOAD);
 
 /// The reload value of the [`systick`](cortex_m::peripheral::SYST) peripheral. Also is the max it can go (2**24).
-const SYSTICK_RELOAD: u32 = 0x00FF_FFFF;
+pub(crate) const SYSTICK_RELOAD: u32 = 0x00FF_FFFF;
 /// the resolution of [`systick`](cortex_m::peripheral::SYST), 2**24
-#[cfg(feature = "extended")]
-const SYSTICK_RESOLUTION: TBContainer = 0x0100_0000;
+#[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
+pub(crate) const SYSTICK_RESOLUTION: TBContainer = 0x0100_0000;
+
+/// Folds the ticks covered by a reload period that just finished (full or shortened) into
+/// [`ELAPSED_TICKS_BASE`], so [`read_ticks`]/[`SysTickTimebase::read_cs`] keep counting correctly
+/// regardless of how many times the live reload was shortened and restored in between.
+///
+/// A reload value of `N` counts down from `N` to `0`, so the period covers `N + 1` ticks (a full
+/// [`SYSTICK_RELOAD`] period is [`SYSTICK_RESOLUTION`] ticks).
+#[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
+pub(crate) fn note_elapsed_period(finished_reload: u32) {
+    ELAPSED_TICKS_BASE.fetch_add(TBContainer::from(finished_reload) + 1, Ordering::Release);
+}
 
 /// [`systick`](cortex_m::peripheral::SYST) timebase.
 ///
@@ -80,6 +172,8 @@ pub struct SysTickTimebase<const FREQ: u32> {
     /// Begrudgingly take the clock frequency by value as well for when we can't use generics
     #[allow(unused)]
     sysclk: u32,
+    /// The instant [`Self::new`] was called, used as the reference point for [`Self::elapsed`].
+    start: TBInstant<FREQ>,
 }
 
 impl<const FREQ: u32> SysTickTimebase<FREQ> {
@@ -100,50 +194,114 @@ impl<const FREQ: u32> SysTickTimebase<FREQ> {
         systick.set_reload(SYSTICK_RELOAD);
         systick.enable_counter();
 
-        #[cfg(feature = "extended")]
+        #[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
         systick.enable_interrupt();
 
-        Self { systick, sysclk }
+        let start = TBInstant::<FREQ>::from_ticks(read_ticks());
+
+        Self {
+            systick,
+            sysclk,
+            start,
+        }
     }
 
     /// Reads the current value from [`systick`](cortex_m::peripheral::SYST).
     #[must_use]
     #[allow(clippy::unused_self)]
     pub fn read(&self) -> TBInstant<FREQ> {
-        // Read SYSTICK and maybe account for rollovers
-        let ticks = {
-            #[cfg(feature = "extended")]
-            {
-                // read the clock & ROLLOVER_COUNT. We read `SYST` twice because we need to detect
-                // if we've rolled over, and if we have make sure we have the right value for ROLLOVER_COUNT.
-                let first = SYST::get_current();
-                let rollover_count: TBContainer = ROLLOVER_COUNT.load(Ordering::Acquire).into();
-                let second = SYST::get_current();
-
-                // Since the SYSTICK counter is a count down timer, check if first is larger than second
-                if first > second {
-                    // The usual case. We did not roll over between the first and second reading,
-                    // and because of that we also know we got a valid read on ROLLOVER_COUNT.
-                    rollover_count * SYSTICK_RESOLUTION + TBContainer::from(SYSTICK_RELOAD - first)
-                } else {
-                    // we rolled over sometime between the first and second read. We may or may not have
-                    // caught the right ROLLOVER_COUNT, so grab that again and then use the second reading.
-                    let rollover_count: TBContainer = ROLLOVER_COUNT.load(Ordering::Acquire).into();
-                    rollover_count * SYSTICK_RESOLUTION + TBContainer::from(SYSTICK_RELOAD - second)
-                }
-            }
+        TBInstant::<FREQ>::from_ticks(read_ticks())
+    }
 
-            #[cfg(not(feature = "extended"))]
-            {
-                // We aren't trying to be fancy here, we don't care if this rolled over from the last read.
-                TBContainer::from(SYSTICK_RELOAD - SYST::get_current())
+    /// Returns the duration between `earlier` and now, or `None` if `earlier` is actually after
+    /// now (e.g. it was read across a non-`extended` 2**24 rollover).
+    #[must_use]
+    pub fn elapsed_since(&self, earlier: TBInstant<FREQ>) -> Option<TBDuration<FREQ>> {
+        self.read().checked_duration_since(earlier)
+    }
+
+    /// Returns the duration since this [`SysTickTimebase`] was constructed.
+    #[must_use]
+    pub fn elapsed(&self) -> Option<TBDuration<FREQ>> {
+        self.elapsed_since(self.start)
+    }
+
+    /// Reads the current value like [`Self::read`], but inside a [`critical_section::with`]
+    /// guard instead of relying on [`Self::read`]'s lock-free double read.
+    ///
+    /// We check [`SCB`]'s `PENDSTSET` bit (whether the `SysTick` exception is pending) rather than
+    /// [`SYST`]'s `COUNTFLAG`: `COUNTFLAG` clears only when read, and nothing else in this crate
+    /// ever reads it, so it would stay set from the very first rollover onward and make every
+    /// later call see a stale, already-serviced wrap. `PENDSTSET` instead is cleared by hardware
+    /// the moment the `SysTick` exception actually runs, so it only ever reflects a `SysTick`
+    /// exception that truly hasn't been serviced yet (a rollover, or one `rtic`'s `set_compare`
+    /// requested for a deadline already passed), and reading it has no side effects of its own.
+    #[cfg(all(
+        feature = "critical-section",
+        any(
+            feature = "extended",
+            feature = "embassy",
+            feature = "rtic",
+            feature = "global"
+        )
+    ))]
+    #[must_use]
+    #[allow(clippy::unused_self)]
+    pub fn read_cs(&self) -> TBInstant<FREQ> {
+        let ticks = critical_section::with(|_cs| {
+            let current = SYST::get_current();
+            let armed_reload: TBContainer = ARMED_RELOAD.load(Ordering::Acquire).into();
+            let mut base = ELAPSED_TICKS_BASE.load(Ordering::Acquire);
+
+            if SCB::is_pendst_pending() {
+                // The SysTick exception is pending but, since we're holding a critical section, it
+                // can't have run yet to account for the period it represents.
+                base += armed_reload + 1;
             }
-        };
+
+            base + (armed_reload - TBContainer::from(current))
+        });
 
         TBInstant::<FREQ>::from_ticks(ticks)
     }
 }
 
+/// Reads the current extended tick count from [`systick`](cortex_m::peripheral::SYST),
+/// accounting for rollovers when any of the `extended`, `embassy`, `rtic` or `global` features
+/// are enabled.
+pub(crate) fn read_ticks() -> TBContainer {
+    // Read SYSTICK and maybe account for rollovers
+    #[cfg(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global"))]
+    {
+        // read the clock, the currently armed reload, and ELAPSED_TICKS_BASE. We read `SYST`
+        // twice because we need to detect if we've rolled over, and if we have make sure we have
+        // the right values for the other two.
+        let first = SYST::get_current();
+        let armed_reload: TBContainer = ARMED_RELOAD.load(Ordering::Acquire).into();
+        let base = ELAPSED_TICKS_BASE.load(Ordering::Acquire);
+        let second = SYST::get_current();
+
+        // Since the SYSTICK counter is a count down timer, check if first is larger than second
+        if first > second {
+            // The usual case. We did not roll over between the first and second reading,
+            // and because of that we also know we got valid reads on `armed_reload` and `base`.
+            base + (armed_reload - TBContainer::from(first))
+        } else {
+            // we rolled over sometime between the first and second read. We may or may not have
+            // caught the right values above, so grab them again and then use the second reading.
+            let armed_reload: TBContainer = ARMED_RELOAD.load(Ordering::Acquire).into();
+            let base = ELAPSED_TICKS_BASE.load(Ordering::Acquire);
+            base + (armed_reload - TBContainer::from(second))
+        }
+    }
+
+    #[cfg(not(any(feature = "extended", feature = "embassy", feature = "rtic", feature = "global")))]
+    {
+        // We aren't trying to be fancy here, we don't care if this rolled over from the last read.
+        TBContainer::from(SYSTICK_RELOAD - SYST::get_current())
+    }
+}
+
 #[cfg(all(feature = "embedded-hal", feature = "container-u64"))]
 impl<const FREQ: u32> DelayUs<u64> for SysTickTimebase<FREQ> {
     fn delay_us(&mut self, us: u64) {
@@ -241,12 +399,46 @@ impl_delay_us!(u8, u16, u32);
 #[cfg(all(feature = "embedded-hal", not(feature = "container-u64")))]
 impl_delay_us!(u8, u16);
 
-#[cfg(feature = "extended")]
+/// embedded-hal 1.0 collapses all of the blocking delay traits above into a single [`DelayNs`].
+#[cfg(feature = "embedded-hal-1")]
+impl<const FREQ: u32> embedded_hal_1::delay::DelayNs for SysTickTimebase<FREQ> {
+    fn delay_ns(&mut self, ns: u32) {
+        let ticks = (u64::from(self.sysclk) * u64::from(ns) / 1_000_000_000) as TBContainer;
+
+        let start = self.read().ticks();
+        let end = start + ticks;
+        let mut previous = start;
+        loop {
+            let time = self.read().ticks();
+            if time >= end {
+                break;
+            }
+            if time < previous {
+                panic!("Detected overflow while delaying");
+            }
+
+            previous = time;
+        }
+    }
+}
+
+#[cfg(all(
+    any(feature = "extended", feature = "global"),
+    not(any(feature = "embassy", feature = "rtic"))
+))]
 use cortex_m_rt::exception;
 
-#[cfg(feature = "extended")]
+// When the `embassy` or `rtic` feature is enabled, this exception is serviced elsewhere
+// (reprogramming the reload for alarms/compares along the way): `embassy` defines its own
+// handler, and with `rtic` the application's `SysTick` binding calls
+// `SysTickMonotonic::on_interrupt`. `global` has no reload-reprogramming of its own, so it shares this plain handler with
+// `extended`.
+#[cfg(all(
+    any(feature = "extended", feature = "global"),
+    not(any(feature = "embassy", feature = "rtic"))
+))]
 #[exception]
 #[allow(non_snake_case)]
 fn SysTick() {
-    ROLLOVER_COUNT.fetch_add(1, Ordering::Release);
+    note_elapsed_period(SYSTICK_RELOAD);
 }
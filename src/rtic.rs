@@ -0,0 +1,105 @@
+//! [`rtic_monotonic::Monotonic`] implementation backed by [`systick`](cortex_m::peripheral::SYST).
+//!
+//! [`SysTick`](cortex_m::peripheral::scb::Exception::SysTick) has no compare register, so
+//! [`set_compare`](Monotonic::set_compare) works by temporarily lowering the reload value so the
+//! exception fires at (or just before) the requested instant, instead of waiting a full 2**24
+//! cycle period. Whenever the reload is shortened or restored, the ticks it covers are folded
+//! into the crate's shared tick accounting, so [`now`](Monotonic::now) stays consistent across
+//! compare firings regardless of how many times the reload gets shortened in between.
+use core::sync::atomic::Ordering;
+
+use cortex_m::peripheral::{SCB, SYST};
+use rtic_monotonic::Monotonic;
+
+use crate::{
+    note_elapsed_period, SysTickTimebase, TBContainer, TBInstant, ARMED_RELOAD, SYSTICK_RELOAD,
+};
+
+/// Wraps a [`SysTickTimebase`] so it can be used as an RTIC 1 `#[monotonic]`.
+pub struct SysTickMonotonic<const FREQ: u32> {
+    timebase: SysTickTimebase<FREQ>,
+}
+
+impl<const FREQ: u32> SysTickMonotonic<FREQ> {
+    /// Wraps an already-configured [`SysTickTimebase`] for use as an RTIC [`Monotonic`].
+    ///
+    /// [`SysTickTimebase::new`] always leaves the full [`SYSTICK_RELOAD`] armed.
+    #[must_use]
+    pub fn new(timebase: SysTickTimebase<FREQ>) -> Self {
+        Self { timebase }
+    }
+}
+
+impl<const FREQ: u32> Monotonic for SysTickMonotonic<FREQ> {
+    type Instant = TBInstant<FREQ>;
+    type Duration = fugit::Duration<TBContainer, 1, FREQ>;
+
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    fn now(&mut self) -> Self::Instant {
+        self.timebase.read()
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        self.timebase.systick.set_reload(SYSTICK_RELOAD);
+        self.timebase.systick.clear_current();
+        self.timebase.systick.enable_interrupt();
+        self.timebase.systick.enable_counter();
+        ARMED_RELOAD.store(SYSTICK_RELOAD, Ordering::Release);
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        // ticks between now and `instant`, clamped to [1, SYSTICK_RELOAD] since we can only
+        // reload a 24 bit countdown and a reload of 0 is not valid.
+        let delta = instant
+            .checked_duration_since(self.now())
+            .map_or(0, |d| d.ticks());
+        let reload = delta.clamp(1, TBContainer::from(SYSTICK_RELOAD));
+
+        // `clear_current()` below discards whatever the in-flight period had already counted, so
+        // fold that partial progress into the shared tick accounting before we throw it away.
+        let armed_reload = ARMED_RELOAD.load(Ordering::Acquire);
+        note_elapsed_period(armed_reload - SYST::get_current());
+
+        self.timebase.systick.disable_counter();
+        self.timebase.systick.set_reload(reload as u32);
+        self.timebase.systick.clear_current();
+        self.timebase.systick.enable_counter();
+        ARMED_RELOAD.store(reload as u32, Ordering::Release);
+
+        if delta == 0 {
+            // the deadline is already behind us: don't wait for `reload` ticks to elapse, ask for
+            // the exception to fire right away.
+            SCB::set_pendst();
+        }
+    }
+
+    fn clear_compare_flag(&mut self) {
+        // Nothing to acknowledge here: `on_interrupt` below reprograms the reload, which is what
+        // actually silences this particular compare.
+    }
+
+    fn on_interrupt(&mut self) {
+        // whatever was armed (full period or a shortened compare) just fully elapsed; fold the
+        // ticks it covered into the shared tick accounting before going back to free-running.
+        note_elapsed_period(ARMED_RELOAD.load(Ordering::Acquire));
+
+        // the compare has fired (or we're servicing the regular 2**24 rollover); go back to
+        // free-running until RTIC asks us to arm another compare via `set_compare`.
+        self.timebase.systick.set_reload(SYSTICK_RELOAD);
+        self.timebase.systick.clear_current();
+        ARMED_RELOAD.store(SYSTICK_RELOAD, Ordering::Release);
+    }
+
+    fn enable_timer(&mut self) {
+        self.timebase.systick.enable_interrupt();
+    }
+
+    fn disable_timer(&mut self) {
+        self.timebase.systick.disable_interrupt();
+    }
+}